@@ -1,11 +1,13 @@
 //! Memory allocation error.
 
 use std::alloc::Layout;
+use std::cell::Cell;
 use std::collections::TryReserveError;
 use std::error::Error;
 use std::fmt;
 use std::panic::{PanicInfo, UnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
 /// The error type for allocation failure.
 #[derive(Copy, Clone)]
@@ -71,14 +73,73 @@ fn alloc_error_hook(layout: Layout) {
 
 type PanicHook = Box<dyn Fn(&PanicInfo<'_>) + 'static + Sync + Send>;
 
+/// The panic hook that was installed before this crate's own hook, captured
+/// by `catch_alloc_error`/`catch_alloc_error_with`, so non-`AllocError` panics can be
+/// forwarded to it instead of unconditionally aborting.
+static PREV_HOOK: OnceLock<PanicHook> = OnceLock::new();
+
+thread_local! {
+    /// Whether a non-`AllocError` panic on this thread should be forwarded
+    /// to [`PREV_HOOK`] rather than aborting. Set from the [`Config`]
+    /// passed to the innermost `catch_alloc_error_with` call currently
+    /// running on this thread, and restored to its previous value when that
+    /// call returns, so nesting and unrelated panics on other threads can't
+    /// race on it.
+    static PROPAGATE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How `catch_alloc_error_with` should handle a panic that is not an
+/// [`AllocError`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PanicMode {
+    /// Abort the process, as `catch_alloc_error` has always done.
+    #[default]
+    Abort,
+    /// Forward the panic to whichever hook was installed before this crate's,
+    /// instead of aborting.
+    Propagate,
+}
+
+/// Configuration for `catch_alloc_error_with`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Config {
+    /// How to handle a panic that is not an [`AllocError`]. Defaults to
+    /// [`PanicMode::Abort`].
+    pub mode: PanicMode,
+}
+
 fn panic_hook(panic_info: &PanicInfo<'_>) {
-    // panic abort except alloc error
-    if !panic_info.payload().is::<AllocError>() {
-        std::process::abort();
+    if panic_info.payload().is::<AllocError>() {
+        #[cfg(feature = "global-allocator")]
+        allocator::ThreadPanic::set_panic();
+        return;
     }
 
-    #[cfg(feature = "global-allocator")]
-    allocator::ThreadPanic::set_panic();
+    if PROPAGATE.with(Cell::get) {
+        if let Some(prev) = PREV_HOOK.get() {
+            prev(panic_info);
+            return;
+        }
+    }
+
+    std::process::abort();
+}
+
+fn ensure_hooks_installed() -> Result<(), AllocError> {
+    static SET_HOOK: AtomicBool = AtomicBool::new(false);
+    if !SET_HOOK.load(Ordering::Acquire) {
+        let hook: PanicHook =
+            Box::try_new(panic_hook).map_err(|_| AllocError::new(Layout::new::<PanicHook>()))?;
+        // Capture whichever hook is currently installed (a user's logging or
+        // backtrace hook, or Rust's own default) before replacing it, so
+        // `PanicMode::Propagate` has somewhere to forward to.
+        let _ = PREV_HOOK.set(std::panic::take_hook());
+        std::panic::set_hook(hook);
+        std::alloc::set_alloc_error_hook(alloc_error_hook);
+        SET_HOOK.store(true, Ordering::Release);
+    }
+
+    Ok(())
 }
 
 /// Invokes a closure, capturing the panic of memory allocation error if one occurs.
@@ -90,19 +151,32 @@ fn panic_hook(panic_info: &PanicInfo<'_>) {
 /// Notes that this function will set panic hook and alloc error hook.
 #[inline]
 pub fn catch_alloc_error<F: FnOnce() -> R + UnwindSafe, R>(f: F) -> Result<R, AllocError> {
-    static SET_HOOK: AtomicBool = AtomicBool::new(false);
-    if !SET_HOOK.load(Ordering::Acquire) {
-        let hook: PanicHook =
-            Box::try_new(panic_hook).map_err(|_| AllocError::new(Layout::new::<PanicHook>()))?;
-        std::panic::set_hook(hook);
-        std::alloc::set_alloc_error_hook(alloc_error_hook);
-        SET_HOOK.store(true, Ordering::Release);
-    }
+    catch_alloc_error_with(Config::default(), f)
+}
+
+/// Like [`catch_alloc_error`], but lets the caller choose via [`Config`] how
+/// a panic that is not an `AllocError` should be handled: the default
+/// [`PanicMode::Abort`], or [`PanicMode::Propagate`] to forward it to
+/// whichever panic hook was installed before this crate's own.
+#[inline]
+pub fn catch_alloc_error_with<F: FnOnce() -> R + UnwindSafe, R>(
+    config: Config,
+    f: F,
+) -> Result<R, AllocError> {
+    ensure_hooks_installed()?;
 
     #[cfg(feature = "global-allocator")]
     allocator::ThreadPanic::try_reserve_mem()?;
 
+    let previous = PROPAGATE.with(|p| p.replace(config.mode == PanicMode::Propagate));
     let result = std::panic::catch_unwind(f);
+    PROPAGATE.with(|p| p.set(previous));
+    // A non-`AllocError` panic only unwinds this far in `Propagate` mode,
+    // after the previous hook has seen it; hand it on to the caller.
+    let result = match result {
+        Err(e) if !e.is::<AllocError>() => std::panic::resume_unwind(e),
+        result => result,
+    };
     match result {
         Ok(r) => Ok(r),
         Err(e) => {
@@ -117,24 +191,45 @@ pub fn catch_alloc_error<F: FnOnce() -> R + UnwindSafe, R>(f: F) -> Result<R, Al
     }
 }
 
+/// Configures the emergency reserve used while unwinding an [`AllocError`].
+///
+/// This pre-allocates a single bump pool of `bytes` bytes on the calling
+/// thread, so that allocations made while panicking (a user panic hook
+/// formatting a message, backtrace capture, etc.) can be satisfied without
+/// hitting the exhausted `System` allocator again. See
+/// [`allocator::ThreadPanic::reserve_with`] for reserving specific layouts
+/// instead of one bump pool.
 #[cfg(feature = "global-allocator")]
-mod allocator {
+#[inline]
+pub fn set_emergency_reserve(bytes: usize) -> Result<(), AllocError> {
+    let layout = Layout::from_size_align(bytes, std::mem::align_of::<usize>())
+        .map_err(|_| AllocError::new(Layout::new::<u8>()))?;
+    allocator::ThreadPanic::reserve_with(&[layout])
+}
+
+#[cfg(feature = "global-allocator")]
+pub(crate) mod allocator {
     use crate::AllocError;
     use std::alloc::{GlobalAlloc, Layout, System};
     use std::cell::{Cell, RefCell};
     use std::ptr::NonNull;
 
     #[global_allocator]
-    static GLOBAL: Alloc = Alloc;
+    static GLOBAL: GlobalAllocator = GlobalAllocator;
 
-    struct Alloc;
+    /// The process-wide [`GlobalAlloc`] that backs the crate's OOM-capture
+    /// machinery, falling back to a thread-local reserve while panicking.
+    pub struct GlobalAllocator;
 
-    unsafe impl GlobalAlloc for Alloc {
+    unsafe impl GlobalAlloc for GlobalAllocator {
         #[inline]
         unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
             let ptr = System.alloc(layout);
 
             if ptr.is_null() && ThreadPanic::is_in_panic() {
+                if let Some(p) = ThreadPanic::take_pool_mem(layout) {
+                    return p.as_ptr();
+                }
                 if let Some(p) = ThreadPanic::take_mem(layout) {
                     return p.as_ptr();
                 }
@@ -145,6 +240,10 @@ mod allocator {
 
         #[inline]
         unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            if ThreadPanic::is_in_panic() && ThreadPanic::give_pool_mem(ptr, layout) {
+                return;
+            }
+
             System.dealloc(ptr, layout)
         }
     }
@@ -227,8 +326,139 @@ mod allocator {
         }
     }
 
+    /// A free region inside one of the pool's reserved chunks, available to
+    /// satisfy an allocation while panicking.
+    #[derive(Clone, Copy)]
+    struct FreeBlock {
+        ptr: NonNull<u8>,
+        size: usize,
+    }
+
+    /// Upper bound on the number of free regions a [`Pool`] tracks at once.
+    ///
+    /// The free list is a fixed-size array, not a `Vec`, and deliberately
+    /// never grows: `Pool::take`/`Pool::give` run from inside
+    /// `GlobalAllocator::alloc`/`dealloc` while a panic is already in
+    /// flight, so if they pushed to a growable `Vec` that needed to
+    /// reallocate, that allocation would re-enter `GlobalAllocator::alloc`
+    /// and reborrow the same thread-local `Pool` that is already mutably
+    /// borrowed by the outer call - a `RefCell` double-borrow panic in the
+    /// middle of an OOM unwind, exactly the abort this pool exists to avoid.
+    const MAX_FREE_BLOCKS: usize = 16;
+
+    /// A configurable bump pool that serves arbitrary-sized allocations made
+    /// while unwinding an `AllocError`, so that a user panic hook or
+    /// backtrace capture allocating memory doesn't hit the exhausted
+    /// `System` allocator again. See [`ThreadPanic::reserve_with`].
+    struct Pool {
+        // The whole chunks obtained from `System`, freed on drop. Only
+        // grows in `reserve_with`, which never runs while panicking.
+        chunks: Vec<(NonNull<u8>, Layout)>,
+        // Regions within `chunks` currently available for reuse: a
+        // fixed-capacity list (see `MAX_FREE_BLOCKS`) so `take`/`give`
+        // never reallocate.
+        free: [Option<FreeBlock>; MAX_FREE_BLOCKS],
+        free_len: usize,
+    }
+
+    impl Pool {
+        const fn new() -> Self {
+            Pool {
+                chunks: Vec::new(),
+                free: [None; MAX_FREE_BLOCKS],
+                free_len: 0,
+            }
+        }
+
+        fn reserve_with(&mut self, sizes: &[Layout]) -> Result<(), AllocError> {
+            self.chunks.reserve(sizes.len());
+
+            for &layout in sizes {
+                let ptr = unsafe { System.alloc(layout) };
+                if ptr.is_null() {
+                    return Err(AllocError::new(layout));
+                }
+                let ptr = unsafe { NonNull::new_unchecked(ptr) };
+                self.chunks.push((ptr, layout));
+                self.push_free(FreeBlock {
+                    ptr,
+                    size: layout.size(),
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Pushes `block` onto the free list, silently dropping it (leaking
+        /// it until this pool is torn down) if the list is already at
+        /// `MAX_FREE_BLOCKS`, rather than growing it.
+        fn push_free(&mut self, block: FreeBlock) {
+            if let Some(slot) = self.free.get_mut(self.free_len) {
+                *slot = Some(block);
+                self.free_len += 1;
+            }
+        }
+
+        fn take(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+            let (size, align) = (layout.size(), layout.align());
+            let index = self.free[..self.free_len].iter().position(|slot| {
+                let block = slot.expect("slot below free_len is occupied");
+                block.size >= size && (block.ptr.as_ptr() as usize).is_multiple_of(align)
+            })?;
+
+            let block = self.free[index]
+                .take()
+                .expect("slot below free_len is occupied");
+            let last = self.free_len - 1;
+            if index != last {
+                self.free[index] = self.free[last].take();
+            }
+            self.free_len = last;
+
+            if block.size > size {
+                let tail = unsafe { block.ptr.as_ptr().add(size) };
+                self.push_free(FreeBlock {
+                    ptr: unsafe { NonNull::new_unchecked(tail) },
+                    size: block.size - size,
+                });
+            }
+
+            Some(block.ptr)
+        }
+
+        /// Returns `true` if `ptr` belongs to one of this pool's reserved
+        /// chunks, in which case it is handed to `push_free` for reuse (and
+        /// leaked until teardown if the free list is full).
+        fn give(&mut self, ptr: NonNull<u8>, layout: Layout) -> bool {
+            let addr = ptr.as_ptr() as usize;
+            let owned = self.chunks.iter().any(|&(chunk_ptr, chunk_layout)| {
+                let start = chunk_ptr.as_ptr() as usize;
+                addr >= start && addr < start + chunk_layout.size()
+            });
+
+            if owned {
+                self.push_free(FreeBlock {
+                    ptr,
+                    size: layout.size(),
+                });
+            }
+
+            owned
+        }
+    }
+
+    impl Drop for Pool {
+        #[inline]
+        fn drop(&mut self) {
+            for (ptr, layout) in self.chunks.drain(..) {
+                unsafe { System.dealloc(ptr.as_ptr(), layout) };
+            }
+        }
+    }
+
     thread_local! {
         static THREAD_PANIC_MEM: RefCell<PanicMem> = RefCell::new(PanicMem::new());
+        static THREAD_POOL: RefCell<Pool> = const { RefCell::new(Pool::new()) };
         static THREAD_IN_PANIC: Cell<bool> = Cell::new(false);
     }
 
@@ -245,6 +475,26 @@ mod allocator {
             THREAD_PANIC_MEM.with(|panic_mem| panic_mem.borrow_mut().take_mem(layout))
         }
 
+        /// Pre-allocates a bump pool covering `sizes` on the current thread,
+        /// used to satisfy arbitrary-sized allocations made while panicking.
+        #[inline]
+        pub fn reserve_with(sizes: &[Layout]) -> Result<(), AllocError> {
+            THREAD_POOL.with(|pool| pool.borrow_mut().reserve_with(sizes))
+        }
+
+        #[inline]
+        pub fn take_pool_mem(layout: Layout) -> Option<NonNull<u8>> {
+            THREAD_POOL.with(|pool| pool.borrow_mut().take(layout))
+        }
+
+        #[inline]
+        pub fn give_pool_mem(ptr: *mut u8, layout: Layout) -> bool {
+            THREAD_POOL.with(|pool| {
+                pool.borrow_mut()
+                    .give(unsafe { NonNull::new_unchecked(ptr) }, layout)
+            })
+        }
+
         #[inline]
         pub fn set_panic() {
             THREAD_IN_PANIC.with(|in_panic| in_panic.set(true))
@@ -0,0 +1,265 @@
+//! The fallible allocator trait.
+
+use crate::alloc::AllocError;
+use std::alloc::{Layout, System};
+use std::ptr::{self, NonNull};
+
+/// A trait for types that can allocate and deallocate memory on behalf of the
+/// crate's fallible containers, reporting failure as an [`AllocError`]
+/// instead of aborting.
+pub trait Alloc {
+    /// Attempts to allocate a block of memory fitting `layout`.
+    ///
+    /// On success, returns a [`NonNull`] pointing at the allocated block; its
+    /// length may be larger than `layout.size()`.
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+    /// Deallocates the block of memory referenced by `ptr`, which must have
+    /// been allocated by this allocator using a layout compatible with
+    /// `layout`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must denote a block of memory currently allocated by this
+    /// allocator, and `layout` must be the layout that block was allocated
+    /// with.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Attempts to extend the block of memory referenced by `ptr` to fit
+    /// `new_layout`, returning [`AllocError`] instead of aborting if
+    /// allocation fails.
+    ///
+    /// The default implementation allocates a new block of `new_layout`,
+    /// copies the first `old_layout.size()` bytes over, and deallocates the
+    /// old block; allocators that can grow in place should override this to
+    /// use `realloc`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must denote a block of memory currently allocated by this
+    /// allocator via a layout compatible with `old_layout`, and
+    /// `new_layout.size()` must be greater than or equal to
+    /// `old_layout.size()`.
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert!(new_layout.size() >= old_layout.size());
+
+        let new_ptr = self.allocate(new_layout)?;
+        if old_layout.size() != 0 {
+            ptr::copy_nonoverlapping(
+                ptr.as_ptr(),
+                new_ptr.as_ptr().cast::<u8>(),
+                old_layout.size(),
+            );
+            self.deallocate(ptr, old_layout);
+        }
+        Ok(new_ptr)
+    }
+
+    /// Behaves like [`Alloc::grow`], except that the newly-allocated tail
+    /// (the bytes past `old_layout.size()`) is zeroed.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Alloc::grow`].
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let new_ptr = self.grow(ptr, old_layout, new_layout)?;
+        let tail = new_ptr.as_ptr().cast::<u8>().add(old_layout.size());
+        ptr::write_bytes(tail, 0, new_layout.size() - old_layout.size());
+        Ok(new_ptr)
+    }
+
+    /// Attempts to shrink the block of memory referenced by `ptr` to fit
+    /// `new_layout`, returning [`AllocError`] instead of aborting if
+    /// allocation fails.
+    ///
+    /// The default implementation allocates a new, smaller block, copies
+    /// the first `new_layout.size()` bytes over, and deallocates the old
+    /// block; allocators that can shrink in place should override this to
+    /// use `realloc`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must denote a block of memory currently allocated by this
+    /// allocator via a layout compatible with `old_layout`, and
+    /// `new_layout.size()` must be less than or equal to
+    /// `old_layout.size()`.
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert!(new_layout.size() <= old_layout.size());
+
+        if new_layout.size() == 0 {
+            if old_layout.size() != 0 {
+                self.deallocate(ptr, old_layout);
+            }
+            return Ok(NonNull::slice_from_raw_parts(
+                NonNull::new_unchecked(new_layout.align() as *mut u8),
+                0,
+            ));
+        }
+
+        let new_ptr = self.allocate(new_layout)?;
+        ptr::copy_nonoverlapping(
+            ptr.as_ptr(),
+            new_ptr.as_ptr().cast::<u8>(),
+            new_layout.size(),
+        );
+        self.deallocate(ptr, old_layout);
+        Ok(new_ptr)
+    }
+}
+
+/// The global memory allocator, backed by [`std::alloc::System`].
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Global;
+
+impl Alloc for Global {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        use std::alloc::Allocator;
+
+        System.allocate(layout).map_err(|_| AllocError::new(layout))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        use std::alloc::Allocator;
+
+        System.deallocate(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        use std::alloc::Allocator;
+
+        System
+            .grow(ptr, old_layout, new_layout)
+            .map_err(|_| AllocError::new(new_layout))
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        use std::alloc::Allocator;
+
+        System
+            .grow_zeroed(ptr, old_layout, new_layout)
+            .map_err(|_| AllocError::new(new_layout))
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        use std::alloc::Allocator;
+
+        System
+            .shrink(ptr, old_layout, new_layout)
+            .map_err(|_| AllocError::new(new_layout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_and_deallocate() {
+        let layout = Layout::new::<u64>();
+        let ptr = Global.allocate(layout).unwrap();
+        assert!(ptr.len() >= layout.size());
+        unsafe { Global.deallocate(ptr.cast(), layout) };
+    }
+
+    /// Only implements `allocate`/`deallocate`, so `grow`/`grow_zeroed`/
+    /// `shrink` below exercise `Alloc`'s default, allocate-copy-deallocate
+    /// bodies rather than an override.
+    struct DefaultsOnly;
+
+    impl Alloc for DefaultsOnly {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_default_grow() {
+        let old_layout = Layout::new::<[u8; 4]>();
+        let new_layout = Layout::new::<[u8; 8]>();
+        unsafe {
+            let ptr = DefaultsOnly.allocate(old_layout).unwrap().cast::<u8>();
+            ptr.as_ptr().write_bytes(0xAB, 4);
+            let grown = DefaultsOnly
+                .grow(ptr, old_layout, new_layout)
+                .unwrap()
+                .cast::<u8>();
+            assert_eq!(std::slice::from_raw_parts(grown.as_ptr(), 4), &[0xAB; 4]);
+            DefaultsOnly.deallocate(grown, new_layout);
+        }
+    }
+
+    #[test]
+    fn test_default_grow_zeroed() {
+        let old_layout = Layout::new::<[u8; 4]>();
+        let new_layout = Layout::new::<[u8; 8]>();
+        unsafe {
+            let ptr = DefaultsOnly.allocate(old_layout).unwrap().cast::<u8>();
+            ptr.as_ptr().write_bytes(0xAB, 4);
+            let grown = DefaultsOnly
+                .grow_zeroed(ptr, old_layout, new_layout)
+                .unwrap()
+                .cast::<u8>();
+            assert_eq!(std::slice::from_raw_parts(grown.as_ptr(), 4), &[0xAB; 4]);
+            assert_eq!(
+                std::slice::from_raw_parts(grown.as_ptr().add(4), 4),
+                &[0; 4]
+            );
+            DefaultsOnly.deallocate(grown, new_layout);
+        }
+    }
+
+    #[test]
+    fn test_default_shrink() {
+        let old_layout = Layout::new::<[u8; 8]>();
+        let new_layout = Layout::new::<[u8; 4]>();
+        unsafe {
+            let ptr = DefaultsOnly.allocate(old_layout).unwrap().cast::<u8>();
+            ptr.as_ptr().write_bytes(0xCD, 4);
+            let shrunk = DefaultsOnly
+                .shrink(ptr, old_layout, new_layout)
+                .unwrap()
+                .cast::<u8>();
+            assert_eq!(std::slice::from_raw_parts(shrunk.as_ptr(), 4), &[0xCD; 4]);
+            DefaultsOnly.deallocate(shrunk, new_layout);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! A fallible, heap-allocated box.
+
+use crate::allocator::{Alloc, Global};
+use crate::AllocError;
+use std::alloc::Layout;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+/// A fallible alternative to [`Box`], whose constructors return an
+/// [`AllocError`] on allocation failure instead of aborting.
+pub struct TryBox<T: ?Sized, A: Alloc = Global> {
+    ptr: NonNull<T>,
+    alloc: A,
+}
+
+unsafe impl<T: ?Sized + Send, A: Alloc + Send> Send for TryBox<T, A> {}
+unsafe impl<T: ?Sized + Sync, A: Alloc + Sync> Sync for TryBox<T, A> {}
+
+impl<T> TryBox<T> {
+    /// Allocates memory on the global allocator and then places `x` into it,
+    /// returning [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new(x: T) -> Result<Self, AllocError> {
+        TryBox::try_new_in(x, Global)
+    }
+
+    /// Constructs a new `TryBox` with uninitialized contents on the global
+    /// allocator, returning [`AllocError`] instead of aborting if allocation
+    /// fails.
+    #[inline]
+    pub fn try_new_uninit() -> Result<TryBox<MaybeUninit<T>>, AllocError> {
+        TryBox::try_new_uninit_in(Global)
+    }
+
+    /// Constructs a new `TryBox` with zeroed contents on the global
+    /// allocator, returning [`AllocError`] instead of aborting if allocation
+    /// fails.
+    #[inline]
+    pub fn try_new_zeroed() -> Result<TryBox<MaybeUninit<T>>, AllocError> {
+        TryBox::try_new_zeroed_in(Global)
+    }
+}
+
+impl<T, A: Alloc> TryBox<T, A> {
+    /// Allocates memory in `alloc` and then places `x` into it, returning
+    /// [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new_in(x: T, alloc: A) -> Result<Self, AllocError> {
+        let mut boxed = TryBox::<T, A>::try_new_uninit_in(alloc)?;
+        unsafe {
+            boxed.as_mut_ptr().write(x);
+            Ok(boxed.assume_init())
+        }
+    }
+
+    /// Constructs a new `TryBox` with uninitialized contents in `alloc`,
+    /// returning [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new_uninit_in(alloc: A) -> Result<TryBox<MaybeUninit<T>, A>, AllocError> {
+        let layout = Layout::new::<T>();
+        let ptr = TryBox::<T, A>::allocate(&alloc, layout)?;
+        Ok(TryBox {
+            ptr: ptr.cast(),
+            alloc,
+        })
+    }
+
+    /// Constructs a new `TryBox` with zeroed contents in `alloc`, returning
+    /// [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new_zeroed_in(alloc: A) -> Result<TryBox<MaybeUninit<T>, A>, AllocError> {
+        let layout = Layout::new::<T>();
+        let ptr = TryBox::<T, A>::allocate(&alloc, layout)?;
+        if layout.size() != 0 {
+            unsafe { ptr::write_bytes(ptr.as_ptr().cast::<u8>(), 0, layout.size()) };
+        }
+        Ok(TryBox {
+            ptr: ptr.cast(),
+            alloc,
+        })
+    }
+}
+
+impl<T: ?Sized, A: Alloc> TryBox<T, A> {
+    fn allocate(alloc: &A, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        Ok(alloc.allocate(layout)?.cast())
+    }
+
+    /// Constructs a `TryBox` from a raw pointer and the allocator it was
+    /// allocated with.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must have been obtained from a prior call to
+    /// [`TryBox::into_raw_with_allocator`] (or an allocation compatible with
+    /// it), and must not be used after this call.
+    #[inline]
+    pub unsafe fn from_raw_in(raw: *mut T, alloc: A) -> Self {
+        TryBox {
+            ptr: NonNull::new_unchecked(raw),
+            alloc,
+        }
+    }
+
+    /// Consumes the `TryBox`, returning the wrapped raw pointer and the
+    /// allocator.
+    ///
+    /// The caller is responsible for freeing the memory, typically by
+    /// converting it back into a `TryBox` with [`TryBox::from_raw_in`].
+    #[inline]
+    pub fn into_raw_with_allocator(b: Self) -> (*mut T, A) {
+        let b = std::mem::ManuallyDrop::new(b);
+        (b.ptr.as_ptr(), unsafe { ptr::read(&b.alloc) })
+    }
+}
+
+impl<T, A: Alloc> TryBox<MaybeUninit<T>, A> {
+    /// Converts to `TryBox<T, A>`.
+    ///
+    /// # Safety
+    ///
+    /// The value must actually have been initialized.
+    #[inline]
+    pub unsafe fn assume_init(self) -> TryBox<T, A> {
+        let (raw, alloc) = TryBox::into_raw_with_allocator(self);
+        TryBox::from_raw_in(raw.cast(), alloc)
+    }
+}
+
+impl<T: ?Sized, A: Alloc> TryBox<T, A> {
+    /// Consumes the `TryBox`, deallocating its memory and returning the
+    /// inner value.
+    #[inline]
+    pub fn into_inner(b: Self) -> T
+    where
+        T: Sized,
+    {
+        let (raw, alloc) = TryBox::into_raw_with_allocator(b);
+        let value = unsafe { ptr::read(raw) };
+        unsafe { TryBox::dealloc(raw, &alloc) };
+        value
+    }
+
+    unsafe fn dealloc(raw: *mut T, alloc: &A) {
+        let layout = Layout::for_value(&*raw);
+        if layout.size() != 0 {
+            alloc.deallocate(NonNull::new_unchecked(raw.cast::<u8>()), layout);
+        }
+    }
+}
+
+fn dangling(layout: Layout) -> NonNull<u8> {
+    // SAFETY: `layout.align()` is always a non-zero power of two.
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
+
+impl<T: ?Sized, A: Alloc> Deref for TryBox<T, A> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> DerefMut for TryBox<T, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Drop for TryBox<T, A> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            TryBox::dealloc(self.ptr.as_ptr(), &self.alloc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_new() {
+        let b = TryBox::try_new(42u64).unwrap();
+        assert_eq!(*b, 42);
+    }
+
+    #[test]
+    fn test_try_new_uninit() {
+        let mut b = TryBox::<u64>::try_new_uninit().unwrap();
+        let b = unsafe {
+            b.as_mut_ptr().write(7);
+            b.assume_init()
+        };
+        assert_eq!(*b, 7);
+    }
+
+    #[test]
+    fn test_try_new_zeroed() {
+        let b = TryBox::<u64>::try_new_zeroed().unwrap();
+        let b = unsafe { b.assume_init() };
+        assert_eq!(*b, 0);
+    }
+
+    #[test]
+    fn test_zero_sized_type() {
+        let b = TryBox::try_new(()).unwrap();
+        assert_eq!(*b, ());
+    }
+
+    #[test]
+    fn test_deref_mut() {
+        let mut b = TryBox::try_new(1u64).unwrap();
+        *b += 1;
+        assert_eq!(*b, 2);
+    }
+}
@@ -4,17 +4,30 @@
 #![feature(alloc_error_hook)]
 #![feature(try_reserve_kind)]
 #![feature(fmt_internals)]
+#![feature(min_specialization)]
 
 mod sealed {
     pub trait Sealed {}
 }
 
 mod alloc;
+mod allocator;
+mod boxed;
 mod fmt;
+mod rc;
 mod string;
+mod sync;
 mod vec;
 
-pub use crate::alloc::{allocator::Alloc, catch_alloc_error, AllocError};
+#[cfg(feature = "global-allocator")]
+pub use crate::alloc::allocator::GlobalAllocator;
+#[cfg(feature = "global-allocator")]
+pub use crate::alloc::set_emergency_reserve;
+pub use crate::alloc::{catch_alloc_error, catch_alloc_error_with, AllocError, Config, PanicMode};
+pub use crate::allocator::{Alloc, Global};
+pub use crate::boxed::TryBox;
 pub use crate::fmt::try_format;
+pub use crate::rc::{TryRc, TryWeak as TryRcWeak};
 pub use crate::string::TryToString;
+pub use crate::sync::{TryArc, TryWeak as TryArcWeak};
 pub use crate::vec::{VecAllocExt, VecExt};
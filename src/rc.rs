@@ -0,0 +1,221 @@
+//! A fallible, single-threaded reference-counted pointer.
+
+use crate::allocator::{Alloc, Global};
+use crate::AllocError;
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+
+struct RcBox<T> {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+    value: T,
+}
+
+/// A fallible alternative to [`std::rc::Rc`], whose only constructors return
+/// an [`AllocError`] on allocation failure instead of aborting.
+pub struct TryRc<T, A: Alloc = Global> {
+    ptr: NonNull<RcBox<T>>,
+    alloc: A,
+}
+
+/// A non-owning, fallible alternative to [`std::rc::Weak`], obtained via
+/// [`TryRc::downgrade`].
+pub struct TryWeak<T, A: Alloc = Global> {
+    ptr: NonNull<RcBox<T>>,
+    alloc: A,
+}
+
+impl<T> TryRc<T> {
+    /// Constructs a new `TryRc<T>` on the global allocator, returning
+    /// [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        TryRc::try_new_in(value, Global)
+    }
+}
+
+impl<T, A: Alloc> TryRc<T, A> {
+    /// Constructs a new `TryRc<T, A>` in `alloc`, returning [`AllocError`]
+    /// instead of aborting if allocation fails.
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        let layout = Layout::new::<RcBox<T>>();
+        let ptr = alloc.allocate(layout)?.cast::<RcBox<T>>();
+        unsafe {
+            ptr.as_ptr().write(RcBox {
+                strong: Cell::new(1),
+                weak: Cell::new(1),
+                value,
+            });
+        }
+        Ok(TryRc { ptr, alloc })
+    }
+
+    /// Returns the number of strong (owning) references to the value.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    /// Returns the number of weak references to the value.
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.get() - 1
+    }
+
+    /// Creates a new [`TryWeak`] pointer to this allocation.
+    pub fn downgrade(this: &Self) -> TryWeak<T, A>
+    where
+        A: Clone,
+    {
+        let inner = this.inner();
+        inner.weak.set(inner.weak.get() + 1);
+        TryWeak {
+            ptr: this.ptr,
+            alloc: this.alloc.clone(),
+        }
+    }
+
+    /// Returns the inner value if `this` is the only strong reference to it,
+    /// otherwise returns `this` back unchanged.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if TryRc::strong_count(&this) != 1 {
+            return Err(this);
+        }
+
+        let this = std::mem::ManuallyDrop::new(this);
+        let value = unsafe { ptr::read(&this.inner().value) };
+
+        let inner = unsafe { this.ptr.as_ref() };
+        inner.strong.set(0);
+        let weak = inner.weak.get() - 1;
+        inner.weak.set(weak);
+        if weak == 0 {
+            unsafe { dealloc(this.ptr, &this.alloc) };
+        }
+
+        Ok(value)
+    }
+
+    fn inner(&self) -> &RcBox<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, A: Alloc + Clone> Clone for TryRc<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let inner = self.inner();
+        inner.strong.set(inner.strong.get() + 1);
+        TryRc {
+            ptr: self.ptr,
+            alloc: self.alloc.clone(),
+        }
+    }
+}
+
+impl<T, A: Alloc> Deref for TryRc<T, A> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: fmt::Debug, A: Alloc> fmt::Debug for TryRc<T, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, A: Alloc> Drop for TryRc<T, A> {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        let strong = inner.strong.get() - 1;
+        inner.strong.set(strong);
+        if strong != 0 {
+            return;
+        }
+
+        unsafe { ptr::drop_in_place(&mut (*self.ptr.as_ptr()).value) };
+
+        let weak = inner.weak.get() - 1;
+        inner.weak.set(weak);
+        if weak == 0 {
+            unsafe { dealloc(self.ptr, &self.alloc) };
+        }
+    }
+}
+
+impl<T, A: Alloc> TryWeak<T, A> {
+    /// Attempts to upgrade this weak pointer to a [`TryRc`], returning
+    /// `None` if the value has already been dropped.
+    pub fn upgrade(&self) -> Option<TryRc<T, A>>
+    where
+        A: Clone,
+    {
+        let inner = unsafe { self.ptr.as_ref() };
+        let strong = inner.strong.get();
+        if strong == 0 {
+            return None;
+        }
+        inner.strong.set(strong + 1);
+        Some(TryRc {
+            ptr: self.ptr,
+            alloc: self.alloc.clone(),
+        })
+    }
+}
+
+impl<T, A: Alloc> Drop for TryWeak<T, A> {
+    fn drop(&mut self) {
+        let inner = unsafe { self.ptr.as_ref() };
+        let weak = inner.weak.get() - 1;
+        inner.weak.set(weak);
+        if weak == 0 {
+            unsafe { dealloc(self.ptr, &self.alloc) };
+        }
+    }
+}
+
+unsafe fn dealloc<T, A: Alloc>(ptr: NonNull<RcBox<T>>, alloc: &A) {
+    alloc.deallocate(ptr.cast::<u8>(), Layout::new::<RcBox<T>>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone_and_drop() {
+        let a = TryRc::try_new(1u64).unwrap();
+        let b = a.clone();
+        assert_eq!(TryRc::strong_count(&a), 2);
+        drop(b);
+        assert_eq!(TryRc::strong_count(&a), 1);
+        assert_eq!(*a, 1);
+    }
+
+    #[test]
+    fn test_downgrade_upgrade() {
+        let a = TryRc::try_new(1u64).unwrap();
+        let weak = TryRc::downgrade(&a);
+        assert_eq!(TryRc::weak_count(&a), 1);
+        assert_eq!(*weak.upgrade().unwrap(), 1);
+        drop(a);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_try_unwrap() {
+        let a = TryRc::try_new(1u64).unwrap();
+        let b = a.clone();
+        let a = TryRc::try_unwrap(a).unwrap_err();
+        drop(b);
+        assert_eq!(TryRc::try_unwrap(a).unwrap(), 1);
+    }
+}
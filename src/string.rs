@@ -1,7 +1,10 @@
 //! String extensions.
 
 use crate::alloc::AllocError;
+use crate::try_format;
+use std::alloc::Layout;
 use std::borrow::Cow;
+use std::fmt;
 
 /// A trait for converting a value to a `String`.
 pub trait TryToString {
@@ -9,6 +12,15 @@ pub trait TryToString {
     fn try_to_string(&self) -> Result<String, AllocError>;
 }
 
+impl<T: fmt::Display + ?Sized> TryToString for T {
+    #[inline]
+    default fn try_to_string(&self) -> Result<String, AllocError> {
+        // `try_format!` loses the failing `Layout` by the time it surfaces
+        // as a `fmt::Error`, so fall back to a generic allocation failure.
+        try_format!("{}", self).map_err(|_| AllocError::new(Layout::new::<u8>()))
+    }
+}
+
 impl TryToString for str {
     #[inline]
     fn try_to_string(&self) -> Result<String, AllocError> {
@@ -41,4 +53,18 @@ mod tests {
     fn test_try_to_string() {
         assert_eq!("abc".try_to_string().unwrap(), "abc");
     }
+
+    #[test]
+    fn test_blanket_display_impl() {
+        assert_eq!(42u64.try_to_string().unwrap(), "42");
+        assert_eq!((-7i32).try_to_string().unwrap(), "-7");
+
+        struct Wrapper;
+        impl fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "wrapper")
+            }
+        }
+        assert_eq!(Wrapper.try_to_string().unwrap(), "wrapper");
+    }
 }
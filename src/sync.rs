@@ -0,0 +1,231 @@
+//! A fallible, thread-safe, atomically reference-counted pointer.
+
+use crate::allocator::{Alloc, Global};
+use crate::AllocError;
+use std::alloc::Layout;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct ArcInner<T> {
+    strong: AtomicUsize,
+    weak: AtomicUsize,
+    value: T,
+}
+
+/// A fallible alternative to [`std::sync::Arc`], whose only constructors
+/// return an [`AllocError`] on allocation failure instead of aborting.
+pub struct TryArc<T, A: Alloc = Global> {
+    ptr: NonNull<ArcInner<T>>,
+    alloc: A,
+}
+
+unsafe impl<T: Sync + Send, A: Alloc + Send> Send for TryArc<T, A> {}
+unsafe impl<T: Sync + Send, A: Alloc + Sync> Sync for TryArc<T, A> {}
+
+/// A non-owning, fallible alternative to [`std::sync::Weak`], obtained via
+/// [`TryArc::downgrade`].
+pub struct TryWeak<T, A: Alloc = Global> {
+    ptr: NonNull<ArcInner<T>>,
+    alloc: A,
+}
+
+unsafe impl<T: Sync + Send, A: Alloc + Send> Send for TryWeak<T, A> {}
+unsafe impl<T: Sync + Send, A: Alloc + Sync> Sync for TryWeak<T, A> {}
+
+impl<T> TryArc<T> {
+    /// Constructs a new `TryArc<T>` on the global allocator, returning
+    /// [`AllocError`] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        TryArc::try_new_in(value, Global)
+    }
+}
+
+impl<T, A: Alloc> TryArc<T, A> {
+    /// Constructs a new `TryArc<T, A>` in `alloc`, returning [`AllocError`]
+    /// instead of aborting if allocation fails.
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        let layout = Layout::new::<ArcInner<T>>();
+        let ptr = alloc.allocate(layout)?.cast::<ArcInner<T>>();
+        unsafe {
+            ptr.as_ptr().write(ArcInner {
+                strong: AtomicUsize::new(1),
+                weak: AtomicUsize::new(1),
+                value,
+            });
+        }
+        Ok(TryArc { ptr, alloc })
+    }
+
+    /// Returns the number of strong (owning) references to the value.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of weak references to the value.
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.load(Ordering::SeqCst) - 1
+    }
+
+    /// Creates a new [`TryWeak`] pointer to this allocation.
+    pub fn downgrade(this: &Self) -> TryWeak<T, A>
+    where
+        A: Clone,
+    {
+        this.inner().weak.fetch_add(1, Ordering::Relaxed);
+        TryWeak {
+            ptr: this.ptr,
+            alloc: this.alloc.clone(),
+        }
+    }
+
+    /// Returns the inner value if `this` is the only strong reference to it,
+    /// otherwise returns `this` back unchanged.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(this);
+        }
+
+        let this = std::mem::ManuallyDrop::new(this);
+        let value = unsafe { ptr::read(&this.inner().value) };
+
+        if this.inner().weak.fetch_sub(1, Ordering::Release) == 1 {
+            unsafe { dealloc(this.ptr, &this.alloc) };
+        }
+
+        Ok(value)
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, A: Alloc + Clone> Clone for TryArc<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        TryArc {
+            ptr: self.ptr,
+            alloc: self.alloc.clone(),
+        }
+    }
+}
+
+impl<T, A: Alloc> Deref for TryArc<T, A> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: fmt::Debug, A: Alloc> fmt::Debug for TryArc<T, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, A: Alloc> Drop for TryArc<T, A> {
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        std::sync::atomic::fence(Ordering::Acquire);
+
+        unsafe { ptr::drop_in_place(&mut (*self.ptr.as_ptr()).value) };
+
+        if self.inner().weak.fetch_sub(1, Ordering::Release) == 1 {
+            unsafe { dealloc(self.ptr, &self.alloc) };
+        }
+    }
+}
+
+impl<T, A: Alloc> TryWeak<T, A> {
+    /// Attempts to upgrade this weak pointer to a [`TryArc`], returning
+    /// `None` if the value has already been dropped.
+    pub fn upgrade(&self) -> Option<TryArc<T, A>>
+    where
+        A: Clone,
+    {
+        let inner = unsafe { self.ptr.as_ref() };
+        let mut strong = inner.strong.load(Ordering::Relaxed);
+        loop {
+            if strong == 0 {
+                return None;
+            }
+            match inner.strong.compare_exchange_weak(
+                strong,
+                strong + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(TryArc {
+                        ptr: self.ptr,
+                        alloc: self.alloc.clone(),
+                    })
+                }
+                Err(actual) => strong = actual,
+            }
+        }
+    }
+}
+
+impl<T, A: Alloc> Drop for TryWeak<T, A> {
+    fn drop(&mut self) {
+        let inner = unsafe { self.ptr.as_ref() };
+        if inner.weak.fetch_sub(1, Ordering::Release) == 1 {
+            unsafe { dealloc(self.ptr, &self.alloc) };
+        }
+    }
+}
+
+unsafe fn dealloc<T, A: Alloc>(ptr: NonNull<ArcInner<T>>, alloc: &A) {
+    alloc.deallocate(ptr.cast::<u8>(), Layout::new::<ArcInner<T>>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone_and_drop() {
+        let a = TryArc::try_new(1u64).unwrap();
+        let b = a.clone();
+        assert_eq!(TryArc::strong_count(&a), 2);
+        drop(b);
+        assert_eq!(TryArc::strong_count(&a), 1);
+        assert_eq!(*a, 1);
+    }
+
+    #[test]
+    fn test_downgrade_upgrade() {
+        let a = TryArc::try_new(1u64).unwrap();
+        let weak = TryArc::downgrade(&a);
+        assert_eq!(TryArc::weak_count(&a), 1);
+        assert_eq!(*weak.upgrade().unwrap(), 1);
+        drop(a);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_try_unwrap() {
+        let a = TryArc::try_new(1u64).unwrap();
+        let b = a.clone();
+        let a = TryArc::try_unwrap(a).unwrap_err();
+        drop(b);
+        assert_eq!(TryArc::try_unwrap(a).unwrap(), 1);
+    }
+}